@@ -1,5 +1,3 @@
-use std::collections::HashMap;
-
 use acir::{
     AcirField,
     circuit::opcodes::MemOp,
@@ -16,11 +14,68 @@ type MemoryIndex = u32;
 /// Maintains the state for solving [`MemoryInit`][`acir::circuit::Opcode::MemoryInit`] and [`MemoryOp`][`acir::circuit::Opcode::MemoryOp`] opcodes.
 #[derive(Default)]
 pub(crate) struct MemoryOpSolver<F> {
-    /// Known values of the memory block, based on the index
-    /// This map evolves as we process the opcodes
-    pub(super) block_value: HashMap<MemoryIndex, F>,
+    /// Known values of the memory block, indexed directly by memory index.
+    /// `init` always populates every index in `0..block_len` contiguously, so this is a dense,
+    /// fully-initialized array rather than a sparse map.
+    pub(super) block_value: Vec<F>,
     /// Length of the block, i.e the number of elements stored into the memory block.
+    /// Always equal to `block_value.len()`.
     pub(super) block_len: u32,
+    /// Write-ahead journal for the current transaction, if one has been started via
+    /// [`MemoryOpSolver::begin`]. Each entry undoes one mutation of `block_value`/`block_len`,
+    /// recorded in the order the mutations happened so that [`MemoryOpSolver::rollback`] can
+    /// undo them in reverse.
+    journal: Option<Vec<JournalEntry<F>>>,
+    /// Trace of every resolved memory access, recorded in order, if tracing has been enabled
+    /// via [`MemoryOpSolver::enable_tracing`].
+    trace: Option<Vec<MemoryAccess<F>>>,
+}
+
+/// A single undo step recorded in the write-ahead journal for an open transaction.
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+enum JournalEntry<F> {
+    /// A [`MemoryOpSolver::write_memory_index`] call; undone by restoring `previous_value` at
+    /// `index`.
+    Write { index: MemoryIndex, previous_value: F },
+    /// A [`MemoryOpSolver::push_memory_index`] call; undone by popping the appended element.
+    Push,
+    /// A [`MemoryOpSolver::pop_memory_index`] call; undone by pushing `value` back onto the
+    /// block.
+    Pop { value: F },
+}
+
+/// Whether a recorded [`MemoryAccess`] was a read from or a write to the memory block.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub(crate) enum MemoryAccessKind {
+    Read,
+    Write,
+}
+
+/// A single resolved memory access produced while solving a
+/// [`MemOp`][acir::circuit::opcodes::MemOp], recorded when tracing is enabled on a
+/// [`MemoryOpSolver`].
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub(crate) struct MemoryAccess<F> {
+    pub(crate) kind: MemoryAccessKind,
+    pub(crate) index: MemoryIndex,
+    pub(crate) value: F,
+    pub(crate) predicate_skipped: bool,
+    pub(crate) opcode_location: ErrorLocation,
+}
+
+/// An owned capture of a [`MemoryOpSolver`]'s in-flight state, taken via [`MemoryOpSolver::snapshot`].
+///
+/// This can be serialized (or sent across a process boundary) and later handed back to
+/// [`MemoryOpSolver::restore`] so that witness generation for a memory block can be suspended
+/// at an opcode boundary and resumed later from exactly where it left off. It also carries any
+/// open transaction's journal and any recorded trace, so that suspending mid-transaction (or
+/// mid-trace) and resuming elsewhere behaves exactly as if solving had never paused.
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub(crate) struct MemoryOpSolverState<F> {
+    block_value: Vec<F>,
+    block_len: u32,
+    journal: Option<Vec<JournalEntry<F>>>,
+    trace: Option<Vec<MemoryAccess<F>>>,
 }
 
 impl<F: AcirField> MemoryOpSolver<F> {
@@ -39,7 +94,7 @@ impl<F: AcirField> MemoryOpSolver<F> {
         }
     }
 
-    /// Update the 'block_value' map with the provided index/value
+    /// Update the `block_value` array slot at the provided index with the provided value.
     /// Returns an 'IndexOutOfBounds' error if the index is outside the block range.
     fn write_memory_index(
         &mut self,
@@ -53,18 +108,90 @@ impl<F: AcirField> MemoryOpSolver<F> {
                 array_size: self.block_len,
             });
         }
-        self.block_value.insert(index, value);
+        if let Some(journal) = &mut self.journal {
+            journal.push(JournalEntry::Write {
+                index,
+                previous_value: self.block_value[index as usize],
+            });
+        }
+        self.block_value[index as usize] = value;
         Ok(())
     }
 
-    /// Returns the value stored in the 'block_value' map for the provided index
-    /// Returns an 'IndexOutOfBounds' error if the index is not in the map.
+    /// Begins a new transaction. While a transaction is in progress, every write made via
+    /// [`Self::write_memory_index`] is journaled so that it can be undone with [`Self::rollback`].
+    pub(crate) fn begin(&mut self) {
+        self.journal = Some(Vec::new());
+    }
+
+    /// Commits the current transaction, discarding the journal so its writes become permanent.
+    pub(crate) fn commit(&mut self) {
+        self.journal = None;
+    }
+
+    /// Rolls back the current transaction, replaying the journal in reverse to restore
+    /// `block_value` to the state it was in before the matching [`Self::begin`] call.
+    pub(crate) fn rollback(&mut self) {
+        if let Some(journal) = self.journal.take() {
+            for entry in journal.into_iter().rev() {
+                match entry {
+                    JournalEntry::Write { index, previous_value } => {
+                        self.block_value[index as usize] = previous_value;
+                    }
+                    JournalEntry::Push => {
+                        self.block_value.pop();
+                        self.block_len -= 1;
+                    }
+                    JournalEntry::Pop { value } => {
+                        self.block_value.push(value);
+                        self.block_len += 1;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Returns the value stored in the `block_value` array at the provided index.
+    /// Returns an 'IndexOutOfBounds' error if the index is outside the block range.
     fn read_memory_index(&self, index: MemoryIndex) -> Result<F, OpcodeResolutionError<F>> {
-        self.block_value.get(&index).copied().ok_or(OpcodeResolutionError::IndexOutOfBounds {
-            opcode_location: ErrorLocation::Unresolved,
-            index: F::from(index as u128),
-            array_size: self.block_len,
-        })
+        self.block_value.get(index as usize).copied().ok_or(
+            OpcodeResolutionError::IndexOutOfBounds {
+                opcode_location: ErrorLocation::Unresolved,
+                index: F::from(index as u128),
+                array_size: self.block_len,
+            },
+        )
+    }
+
+    /// Appends `value` to the end of the memory block, growing the block (and `block_len`) by
+    /// one element. This lets a block grow dynamically at runtime instead of being fixed to the
+    /// length given at `init` time, to model stack-style memory regions on top of ACIR memory.
+    fn push_memory_index(&mut self, value: F) {
+        if let Some(journal) = &mut self.journal {
+            journal.push(JournalEntry::Push);
+        }
+        self.block_value.push(value);
+        self.block_len += 1;
+    }
+
+    /// Removes and returns the value at the end of the memory block, shrinking the block (and
+    /// `block_len`) by one element.
+    /// Returns a [`OpcodeResolutionError::MemoryStackUnderflow`] error if the block is empty.
+    fn pop_memory_index(&mut self) -> Result<F, OpcodeResolutionError<F>> {
+        if self.block_len == 0 {
+            return Err(OpcodeResolutionError::MemoryStackUnderflow {
+                opcode_location: ErrorLocation::Unresolved,
+            });
+        }
+        let value = self
+            .block_value
+            .pop()
+            .expect("block_len > 0 guarantees block_value is non-empty");
+        self.block_len -= 1;
+        if let Some(journal) = &mut self.journal {
+            journal.push(JournalEntry::Pop { value });
+        }
+        Ok(value)
     }
 
     /// Set the block_value from a MemoryInit opcode
@@ -73,13 +200,16 @@ impl<F: AcirField> MemoryOpSolver<F> {
         init: &[Witness],
         initial_witness: &WitnessMap<F>,
     ) -> Result<(), OpcodeResolutionError<F>> {
-        self.block_len = init.len() as u32;
-        for (memory_index, witness) in init.iter().enumerate() {
-            self.write_memory_index(
-                memory_index as MemoryIndex,
-                *witness_to_value(initial_witness, *witness)?,
-            )?;
+        // Collect into a local `Vec` first and only commit it (along with `block_len`) once
+        // every witness has resolved successfully. Otherwise an error partway through would
+        // leave `block_len` ahead of `block_value.len()`, and a later in-bounds write would
+        // index out of bounds on the (too short) vec instead of resolving cleanly.
+        let mut block_value = Vec::with_capacity(init.len());
+        for witness in init {
+            block_value.push(*witness_to_value(initial_witness, *witness)?);
         }
+        self.block_len = block_value.len() as u32;
+        self.block_value = block_value;
         Ok(())
     }
 
@@ -87,8 +217,10 @@ impl<F: AcirField> MemoryOpSolver<F> {
     /// The opcode 'op' contains the index and value of the operation and the type
     /// of the operation.
     /// They are all stored as an [Expression]
-    /// The type of 'operation' is '0' for a read and '1' for a write. It must be a constant
-    /// expression.
+    /// The type of 'operation' is '0' for a read, '1' for a write, '2' for a stack-style push
+    /// (appends 'op.value' to the end of the block, growing it by one element) and '3' for a
+    /// stack-style pop (removes the last element of the block into 'op.value', shrinking it by
+    /// one element). It must be a constant expression.
     /// Index is not required to be constant but it must reduce to a known value
     /// for processing the opcode. This is done by doing the (partial) evaluation of its expression,
     /// using the provided witness map.
@@ -100,6 +232,15 @@ impl<F: AcirField> MemoryOpSolver<F> {
     /// WRITE: update the block at index 'op.index' with 'op.value'
     /// - 'op.value' must reduce to a known value
     ///
+    /// PUSH: append 'op.value' to the end of the block, growing it by one element
+    /// - 'op.value' must reduce to a known value
+    /// - 'op.index' is not used
+    ///
+    /// POP: remove the last element of the block into 'op.value', shrinking it by one element
+    /// - 'op.value' must reduce to a witness (after the evaluation of its expression)
+    /// - 'op.index' is not used
+    /// - returns a 'MemoryStackUnderflow' error if the block is already empty
+    ///
     /// If a requirement is not met, it returns an error.
     pub(crate) fn solve_memory_op(
         &mut self,
@@ -107,6 +248,7 @@ impl<F: AcirField> MemoryOpSolver<F> {
         initial_witness: &mut WitnessMap<F>,
         predicate: &Option<Expression<F>>,
         pedantic_solving: bool,
+        opcode_location: ErrorLocation,
     ) -> Result<(), OpcodeResolutionError<F>> {
         let operation = get_value(&op.operation, initial_witness)?;
 
@@ -120,14 +262,80 @@ impl<F: AcirField> MemoryOpSolver<F> {
         // In write operations, this corresponds to the expression which will be written to memory.
         let value = ExpressionSolver::evaluate(&op.value, initial_witness);
 
-        // `operation == 0` implies a read operation. (`operation == 1` implies write operation).
+        // `operation == 0` implies a read operation, `operation == 1` a write operation,
+        // `operation == 2` a push operation and `operation == 3` a pop operation.
         let is_read_operation = operation.is_zero();
+        let is_push_operation = operation == F::from(2u128);
+        let is_pop_operation = operation == F::from(3u128);
 
-        // Fetch whether or not the predicate is false (e.g. equal to zero)
-        let opcode_location = ErrorLocation::Unresolved;
+        // Fetch whether or not the predicate is false (e.g. equal to zero). `opcode_location` is
+        // the caller-supplied position of this opcode; it is threaded through (rather than using
+        // a hardcoded `ErrorLocation::Unresolved`) so that recorded `MemoryAccess` trace entries
+        // can actually be tied back to the opcode that produced them.
         let skip_operation =
             is_predicate_false(initial_witness, predicate, pedantic_solving, &opcode_location)?;
 
+        if is_push_operation {
+            // `arr.push(value_to_push)`
+            //
+            // A zero predicate indicates that we should skip the push operation.
+            if skip_operation {
+                if let Some(trace) = &mut self.trace {
+                    trace.push(MemoryAccess {
+                        kind: MemoryAccessKind::Write,
+                        index: self.block_len,
+                        value: F::zero(),
+                        predicate_skipped: true,
+                        opcode_location,
+                    });
+                }
+                return Ok(());
+            }
+            let value_to_push = get_value(&value, initial_witness)?;
+            self.push_memory_index(value_to_push);
+            if let Some(trace) = &mut self.trace {
+                trace.push(MemoryAccess {
+                    kind: MemoryAccessKind::Write,
+                    index: self.block_len - 1,
+                    value: value_to_push,
+                    predicate_skipped: false,
+                    opcode_location,
+                });
+            }
+            return Ok(());
+        }
+
+        if is_pop_operation {
+            // `value_popped = arr.pop()`
+            let value_read_witness = value.to_witness().expect(
+                "Memory must be read into a specified witness index, encountered an Expression",
+            );
+
+            // A zero predicate indicates that we should skip the pop operation
+            // and zero out the operation's output.
+            //
+            // `traced_index` is the slot the pop reads from: once `pop_memory_index` runs,
+            // `self.block_len` has already been decremented down to that slot, but when the
+            // operation is skipped `self.block_len` is unchanged, so the slot below it is used
+            // instead so the trace is consistent whether or not the pop actually executed.
+            let (value_popped, traced_index) = if skip_operation {
+                (F::zero(), self.block_len.saturating_sub(1))
+            } else {
+                let popped = self.pop_memory_index()?;
+                (popped, self.block_len)
+            };
+            if let Some(trace) = &mut self.trace {
+                trace.push(MemoryAccess {
+                    kind: MemoryAccessKind::Read,
+                    index: traced_index,
+                    value: value_popped,
+                    predicate_skipped: skip_operation,
+                    opcode_location,
+                });
+            }
+            return insert_value(&value_read_witness, value_popped, initial_witness);
+        }
+
         if is_read_operation {
             // `value_read = arr[memory_index]`
             //
@@ -141,6 +349,15 @@ impl<F: AcirField> MemoryOpSolver<F> {
             // and zero out the operation's output.
             let value_in_array =
                 if skip_operation { F::zero() } else { self.read_memory_index(memory_index)? };
+            if let Some(trace) = &mut self.trace {
+                trace.push(MemoryAccess {
+                    kind: MemoryAccessKind::Read,
+                    index: memory_index,
+                    value: value_in_array,
+                    predicate_skipped: skip_operation,
+                    opcode_location,
+                });
+            }
             insert_value(&value_read_witness, value_in_array, initial_witness)
         } else {
             // `arr[memory_index] = value_write`
@@ -151,15 +368,68 @@ impl<F: AcirField> MemoryOpSolver<F> {
 
             // A zero predicate indicates that we should skip the write operation.
             if skip_operation {
+                if let Some(trace) = &mut self.trace {
+                    trace.push(MemoryAccess {
+                        kind: MemoryAccessKind::Write,
+                        index: memory_index,
+                        value: F::zero(),
+                        predicate_skipped: true,
+                        opcode_location,
+                    });
+                }
                 // We only want to write to already initialized memory.
                 // Do nothing if the predicate is zero.
                 Ok(())
             } else {
                 let value_to_write = get_value(&value_write, initial_witness)?;
-                self.write_memory_index(memory_index, value_to_write)
+                self.write_memory_index(memory_index, value_to_write)?;
+                if let Some(trace) = &mut self.trace {
+                    trace.push(MemoryAccess {
+                        kind: MemoryAccessKind::Write,
+                        index: memory_index,
+                        value: value_to_write,
+                        predicate_skipped: false,
+                        opcode_location,
+                    });
+                }
+                Ok(())
             }
         }
     }
+
+    /// Enables trace recording; every subsequent call to [`Self::solve_memory_op`] will append
+    /// an entry to the trace for the memory access it resolves.
+    pub(crate) fn enable_tracing(&mut self) {
+        self.trace = Some(Vec::new());
+    }
+
+    /// Returns the recorded memory access trace, disabling further recording until
+    /// [`Self::enable_tracing`] is called again.
+    pub(crate) fn take_trace(&mut self) -> Vec<MemoryAccess<F>> {
+        self.trace.take().unwrap_or_default()
+    }
+
+    /// Captures the current in-flight memory block state so that solving can be suspended
+    /// (e.g. to service a foreign call or oracle) and later resumed via [`Self::restore`],
+    /// including any open transaction's journal and any in-progress trace.
+    pub(crate) fn snapshot(&self) -> MemoryOpSolverState<F> {
+        MemoryOpSolverState {
+            block_value: self.block_value.clone(),
+            block_len: self.block_len,
+            journal: self.journal.clone(),
+            trace: self.trace.clone(),
+        }
+    }
+
+    /// Reinstates a previously captured [`MemoryOpSolverState`], replacing the solver's
+    /// current memory block state, including any open transaction's journal and any
+    /// in-progress trace.
+    pub(crate) fn restore(&mut self, state: MemoryOpSolverState<F>) {
+        self.block_value = state.block_value;
+        self.block_len = state.block_len;
+        self.journal = state.journal;
+        self.trace = state.trace;
+    }
 }
 
 #[cfg(test)]
@@ -173,10 +443,29 @@ mod tests {
     };
 
     use super::MemoryOpSolver;
+    use crate::pwg::ErrorLocation;
 
     // use pedantic_solving for tests
     const PEDANTIC_SOLVING: bool = true;
 
+    /// Builds a push `MemOp` (`operation == 2`); `op.index` is unused for pushes.
+    fn push_op(value: Expression<FieldElement>) -> MemOp<FieldElement> {
+        MemOp {
+            operation: FieldElement::from(2u128).into(),
+            index: FieldElement::from(0u128).into(),
+            value,
+        }
+    }
+
+    /// Builds a pop `MemOp` (`operation == 3`); `op.index` is unused for pops.
+    fn pop_op(witness: Witness) -> MemOp<FieldElement> {
+        MemOp {
+            operation: FieldElement::from(3u128).into(),
+            index: FieldElement::from(0u128).into(),
+            value: witness.into(),
+        }
+    }
+
     #[test]
     fn test_solver() {
         let mut initial_witness = WitnessMap::from(BTreeMap::from_iter([
@@ -197,7 +486,13 @@ mod tests {
 
         for op in trace {
             block_solver
-                .solve_memory_op(&op, &mut initial_witness, &None, PEDANTIC_SOLVING)
+                .solve_memory_op(
+                    &op,
+                    &mut initial_witness,
+                    &None,
+                    PEDANTIC_SOLVING,
+                    ErrorLocation::Unresolved,
+                )
                 .unwrap();
         }
 
@@ -224,7 +519,13 @@ mod tests {
         for op in invalid_trace {
             if err.is_none() {
                 err = block_solver
-                    .solve_memory_op(&op, &mut initial_witness, &None, PEDANTIC_SOLVING)
+                    .solve_memory_op(
+                        &op,
+                        &mut initial_witness,
+                        &None,
+                        PEDANTIC_SOLVING,
+                        ErrorLocation::Unresolved,
+                    )
                     .err();
             }
         }
@@ -264,6 +565,7 @@ mod tests {
                         &mut initial_witness,
                         &Some(Expression::zero()),
                         PEDANTIC_SOLVING,
+                        ErrorLocation::Unresolved,
                     )
                     .err();
             }
@@ -301,6 +603,7 @@ mod tests {
                         &mut initial_witness,
                         &Some(Expression::zero()),
                         PEDANTIC_SOLVING,
+                        ErrorLocation::Unresolved,
                     )
                     .err();
             }
@@ -312,4 +615,372 @@ mod tests {
         assert_eq!(initial_witness[&Witness(4)], FieldElement::from(0u128));
         assert_eq!(initial_witness[&Witness(5)], FieldElement::from(0u128));
     }
+
+    #[test]
+    fn test_init_error_leaves_block_len_and_block_value_in_sync() {
+        // Witness(2) is never inserted into the witness map, so resolving it while processing
+        // the `MemoryInit` must fail partway through.
+        let initial_witness =
+            WitnessMap::from(BTreeMap::from_iter([(Witness(1), FieldElement::from(1u128))]));
+
+        let init = vec![Witness(1), Witness(2)];
+
+        let mut block_solver = MemoryOpSolver::default();
+        assert!(block_solver.init(&init, &initial_witness).is_err());
+
+        // `block_len` must not be left ahead of `block_value`, or a subsequent write within
+        // `0..block_len` would index out of bounds on the vec instead of resolving cleanly.
+        assert_eq!(block_solver.block_len, 0);
+        assert_eq!(block_solver.block_value.len(), 0);
+    }
+
+    #[test]
+    fn test_push_pop_round_trip() {
+        let mut initial_witness = WitnessMap::from(BTreeMap::from_iter([
+            (Witness(1), FieldElement::from(10u128)),
+            (Witness(2), FieldElement::from(20u128)),
+        ]));
+
+        let mut block_solver = MemoryOpSolver::default();
+        block_solver.init(&[], &initial_witness).unwrap();
+
+        block_solver
+            .solve_memory_op(
+                &push_op(Witness(1).into()),
+                &mut initial_witness,
+                &None,
+                PEDANTIC_SOLVING,
+                ErrorLocation::Unresolved,
+            )
+            .unwrap();
+        block_solver
+            .solve_memory_op(
+                &push_op(Witness(2).into()),
+                &mut initial_witness,
+                &None,
+                PEDANTIC_SOLVING,
+                ErrorLocation::Unresolved,
+            )
+            .unwrap();
+        assert_eq!(block_solver.block_len, 2);
+
+        block_solver
+            .solve_memory_op(
+                &pop_op(Witness(3)),
+                &mut initial_witness,
+                &None,
+                PEDANTIC_SOLVING,
+                ErrorLocation::Unresolved,
+            )
+            .unwrap();
+        block_solver
+            .solve_memory_op(
+                &pop_op(Witness(4)),
+                &mut initial_witness,
+                &None,
+                PEDANTIC_SOLVING,
+                ErrorLocation::Unresolved,
+            )
+            .unwrap();
+
+        // Stack order: last pushed is first popped.
+        assert_eq!(initial_witness[&Witness(3)], FieldElement::from(20u128));
+        assert_eq!(initial_witness[&Witness(4)], FieldElement::from(10u128));
+        assert_eq!(block_solver.block_len, 0);
+    }
+
+    #[test]
+    fn test_pop_underflow() {
+        let mut initial_witness = WitnessMap::default();
+        let mut block_solver = MemoryOpSolver::default();
+        block_solver.init(&[], &initial_witness).unwrap();
+
+        let err = block_solver
+            .solve_memory_op(
+                &pop_op(Witness(1)),
+                &mut initial_witness,
+                &None,
+                PEDANTIC_SOLVING,
+                ErrorLocation::Unresolved,
+            )
+            .unwrap_err();
+
+        assert!(matches!(
+            err,
+            crate::pwg::OpcodeResolutionError::MemoryStackUnderflow { opcode_location: _ }
+        ));
+    }
+
+    #[test]
+    fn test_rollback_undoes_interleaved_write_push_and_pop() {
+        let mut initial_witness = WitnessMap::from(BTreeMap::from_iter([
+            (Witness(1), FieldElement::from(10u128)),
+            (Witness(2), FieldElement::from(20u128)),
+            (Witness(3), FieldElement::from(30u128)),
+            (Witness(4), FieldElement::from(40u128)),
+            (Witness(5), FieldElement::from(99u128)),
+            (Witness(6), FieldElement::from(100u128)),
+            (Witness(7), FieldElement::from(200u128)),
+        ]));
+
+        let init = vec![Witness(1), Witness(2), Witness(3), Witness(4)];
+        let mut block_solver = MemoryOpSolver::default();
+        block_solver.init(&init, &initial_witness).unwrap();
+
+        block_solver.begin();
+
+        // Journal a write to the last initialized index...
+        block_solver
+            .solve_memory_op(
+                &MemOp::write_to_mem_index(FieldElement::from(3u128).into(), Witness(5).into()),
+                &mut initial_witness,
+                &None,
+                PEDANTIC_SOLVING,
+                ErrorLocation::Unresolved,
+            )
+            .unwrap();
+        // ...then grow the block...
+        block_solver
+            .solve_memory_op(
+                &push_op(Witness(6).into()),
+                &mut initial_witness,
+                &None,
+                PEDANTIC_SOLVING,
+                ErrorLocation::Unresolved,
+            )
+            .unwrap();
+        block_solver
+            .solve_memory_op(
+                &push_op(Witness(7).into()),
+                &mut initial_witness,
+                &None,
+                PEDANTIC_SOLVING,
+                ErrorLocation::Unresolved,
+            )
+            .unwrap();
+        // ...then shrink it back past the index written above.
+        let pop_witnesses = [Witness(8), Witness(9), Witness(10), Witness(11)];
+        for (i, witness) in pop_witnesses.into_iter().enumerate() {
+            block_solver
+                .solve_memory_op(
+                    &pop_op(witness),
+                    &mut initial_witness,
+                    &None,
+                    PEDANTIC_SOLVING,
+                    ErrorLocation::Unresolved,
+                )
+                .unwrap_or_else(|e| panic!("pop {i} failed: {e:?}"));
+        }
+        assert_eq!(block_solver.block_len, 2);
+
+        // Without journaling pushes/pops, replaying the write's undo here would index out of
+        // bounds on the now-shorter `block_value` and panic.
+        block_solver.rollback();
+
+        assert_eq!(block_solver.block_len, 4);
+        assert_eq!(
+            block_solver.block_value,
+            vec![
+                FieldElement::from(10u128),
+                FieldElement::from(20u128),
+                FieldElement::from(30u128),
+                FieldElement::from(40u128),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_transaction_commit_keeps_writes() {
+        let mut initial_witness = WitnessMap::from(BTreeMap::from_iter([
+            (Witness(1), FieldElement::from(1u128)),
+            (Witness(2), FieldElement::from(2u128)),
+            (Witness(3), FieldElement::from(99u128)),
+        ]));
+
+        let init = vec![Witness(1), Witness(2)];
+        let mut block_solver = MemoryOpSolver::default();
+        block_solver.init(&init, &initial_witness).unwrap();
+
+        block_solver.begin();
+        block_solver
+            .solve_memory_op(
+                &MemOp::write_to_mem_index(FieldElement::from(0u128).into(), Witness(3).into()),
+                &mut initial_witness,
+                &None,
+                PEDANTIC_SOLVING,
+                ErrorLocation::Unresolved,
+            )
+            .unwrap();
+        block_solver.commit();
+
+        // A committed transaction's writes must survive even if `rollback` is (incorrectly)
+        // called afterwards, since `commit` clears the journal.
+        block_solver.rollback();
+        assert_eq!(block_solver.block_value[0], FieldElement::from(99u128));
+    }
+
+    #[test]
+    fn test_rollback_undoes_writes_from_open_transaction() {
+        let mut initial_witness = WitnessMap::from(BTreeMap::from_iter([
+            (Witness(1), FieldElement::from(1u128)),
+            (Witness(2), FieldElement::from(2u128)),
+            (Witness(3), FieldElement::from(99u128)),
+        ]));
+
+        let init = vec![Witness(1), Witness(2)];
+        let mut block_solver = MemoryOpSolver::default();
+        block_solver.init(&init, &initial_witness).unwrap();
+
+        block_solver.begin();
+        block_solver
+            .solve_memory_op(
+                &MemOp::write_to_mem_index(FieldElement::from(0u128).into(), Witness(3).into()),
+                &mut initial_witness,
+                &None,
+                PEDANTIC_SOLVING,
+                ErrorLocation::Unresolved,
+            )
+            .unwrap();
+        block_solver.rollback();
+
+        assert_eq!(block_solver.block_value[0], FieldElement::from(1u128));
+    }
+
+    #[test]
+    fn test_rollback_after_failed_write_undoes_earlier_writes_in_the_same_transaction() {
+        let mut initial_witness = WitnessMap::from(BTreeMap::from_iter([
+            (Witness(1), FieldElement::from(1u128)),
+            (Witness(2), FieldElement::from(2u128)),
+            (Witness(3), FieldElement::from(99u128)),
+        ]));
+
+        let init = vec![Witness(1), Witness(2)];
+        let mut block_solver = MemoryOpSolver::default();
+        block_solver.init(&init, &initial_witness).unwrap();
+
+        block_solver.begin();
+        // This write succeeds and gets journaled.
+        block_solver
+            .solve_memory_op(
+                &MemOp::write_to_mem_index(FieldElement::from(0u128).into(), Witness(3).into()),
+                &mut initial_witness,
+                &None,
+                PEDANTIC_SOLVING,
+                ErrorLocation::Unresolved,
+            )
+            .unwrap();
+        // This one is out of bounds and errors out, leaving the transaction open.
+        let err = block_solver
+            .solve_memory_op(
+                &MemOp::write_to_mem_index(FieldElement::from(5u128).into(), Witness(3).into()),
+                &mut initial_witness,
+                &None,
+                PEDANTIC_SOLVING,
+                ErrorLocation::Unresolved,
+            )
+            .unwrap_err();
+        assert!(matches!(err, crate::pwg::OpcodeResolutionError::IndexOutOfBounds { .. }));
+
+        block_solver.rollback();
+
+        assert_eq!(block_solver.block_value[0], FieldElement::from(1u128));
+    }
+
+    #[test]
+    fn test_snapshot_restore_round_trip() {
+        let mut initial_witness = WitnessMap::from(BTreeMap::from_iter([
+            (Witness(1), FieldElement::from(1u128)),
+            (Witness(2), FieldElement::from(2u128)),
+            (Witness(3), FieldElement::from(99u128)),
+        ]));
+
+        let init = vec![Witness(1), Witness(2)];
+        let mut block_solver = MemoryOpSolver::default();
+        block_solver.init(&init, &initial_witness).unwrap();
+
+        let snapshot = block_solver.snapshot();
+
+        // Mutate the live solver after taking the snapshot.
+        block_solver
+            .solve_memory_op(
+                &MemOp::write_to_mem_index(FieldElement::from(0u128).into(), Witness(3).into()),
+                &mut initial_witness,
+                &None,
+                PEDANTIC_SOLVING,
+                ErrorLocation::Unresolved,
+            )
+            .unwrap();
+        assert_eq!(block_solver.block_value[0], FieldElement::from(99u128));
+
+        // Restoring the snapshot on a fresh solver reproduces the pre-mutation state exactly.
+        let mut restored_solver = MemoryOpSolver::default();
+        restored_solver.restore(snapshot);
+        assert_eq!(restored_solver.block_value[0], FieldElement::from(1u128));
+        assert_eq!(restored_solver.block_len, 2);
+    }
+
+    #[test]
+    fn test_snapshot_restore_preserves_open_transaction() {
+        let mut initial_witness = WitnessMap::from(BTreeMap::from_iter([
+            (Witness(1), FieldElement::from(1u128)),
+            (Witness(2), FieldElement::from(2u128)),
+            (Witness(3), FieldElement::from(99u128)),
+        ]));
+
+        let init = vec![Witness(1), Witness(2)];
+        let mut block_solver = MemoryOpSolver::default();
+        block_solver.init(&init, &initial_witness).unwrap();
+
+        block_solver.begin();
+        block_solver
+            .solve_memory_op(
+                &MemOp::write_to_mem_index(FieldElement::from(0u128).into(), Witness(3).into()),
+                &mut initial_witness,
+                &None,
+                PEDANTIC_SOLVING,
+                ErrorLocation::Unresolved,
+            )
+            .unwrap();
+
+        // Pause mid-transaction and resume on a fresh solver.
+        let snapshot = block_solver.snapshot();
+        let mut restored_solver = MemoryOpSolver::default();
+        restored_solver.restore(snapshot);
+
+        // The journal must have come along, so a `rollback` on the restored solver still undoes
+        // the write made before the pause instead of being a no-op.
+        restored_solver.rollback();
+        assert_eq!(restored_solver.block_value[0], FieldElement::from(1u128));
+    }
+
+    #[test]
+    fn test_trace_records_caller_supplied_opcode_location() {
+        let mut initial_witness = WitnessMap::from(BTreeMap::from_iter([
+            (Witness(1), FieldElement::from(1u128)),
+            (Witness(2), FieldElement::from(2u128)),
+            (Witness(3), FieldElement::from(99u128)),
+        ]));
+
+        let init = vec![Witness(1), Witness(2)];
+        let mut block_solver = MemoryOpSolver::default();
+        block_solver.init(&init, &initial_witness).unwrap();
+        block_solver.enable_tracing();
+
+        let opcode_location = ErrorLocation::Resolved(acir::circuit::OpcodeLocation::Acir(7));
+        block_solver
+            .solve_memory_op(
+                &MemOp::write_to_mem_index(FieldElement::from(0u128).into(), Witness(3).into()),
+                &mut initial_witness,
+                &None,
+                PEDANTIC_SOLVING,
+                opcode_location.clone(),
+            )
+            .unwrap();
+
+        let trace = block_solver.take_trace();
+        assert_eq!(trace.len(), 1);
+        // Each entry's `opcode_location` must reflect the position passed in by the caller, not
+        // a placeholder, or the trace can't be used to locate which opcode produced it.
+        assert_eq!(trace[0].opcode_location, opcode_location);
+    }
 }
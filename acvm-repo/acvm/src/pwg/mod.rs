@@ -0,0 +1,75 @@
+//! Opcode solving support shared by the program-witness-generation (pwg) submodules.
+//!
+//! This file holds the error types returned by [`memory_op`], [`ErrorLocation`] (the type used
+//! to tag an error or a recorded trace entry with the opcode that produced it), and
+//! [`MemorySolvers`], which routes each memory opcode to the
+//! [`memory_op::MemoryOpSolver::solve_memory_op`] of its block. It does not define the
+//! `arithmetic` module or the `get_value`, `insert_value`, `is_predicate_false` and
+//! `witness_to_value` helpers that [`memory_op`] imports from it, nor the `ACVM` struct, other
+//! opcode kinds, black box functions or Brillig.
+
+use std::collections::HashMap;
+
+use acir::{
+    circuit::opcodes::{BlockId, MemOp},
+    native_types::{Expression, WitnessMap},
+    AcirField,
+};
+
+pub(crate) mod memory_op;
+
+use memory_op::MemoryOpSolver;
+
+/// Where, in the circuit being solved, an [`OpcodeResolutionError`] (or a recorded memory
+/// access) occurred.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub(crate) enum ErrorLocation {
+    /// The opcode position hasn't been resolved yet (e.g. while solving a sub-expression).
+    Unresolved,
+    /// The position of the opcode that produced the error.
+    Resolved(acir::circuit::OpcodeLocation),
+}
+
+/// An error that occurred while resolving an opcode against a witness map.
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error)]
+pub(crate) enum OpcodeResolutionError<F> {
+    #[error("Index out of bounds, array has size {array_size:?}, but index was {index:?}")]
+    IndexOutOfBounds { opcode_location: ErrorLocation, index: F, array_size: u32 },
+    #[error("Attempted to pop a value from an empty memory block")]
+    MemoryStackUnderflow { opcode_location: ErrorLocation },
+}
+
+/// Tracks one [`MemoryOpSolver`] per memory block, keyed by its [`BlockId`]. A block's solver
+/// is created empty the first time a `MemoryOp` for that block is solved; this type does not
+/// handle `MemoryInit` opcodes.
+#[derive(Default)]
+pub(crate) struct MemorySolvers<F> {
+    block_solvers: HashMap<BlockId, MemoryOpSolver<F>>,
+}
+
+impl<F: AcirField> MemorySolvers<F> {
+    /// Solves a single [`Opcode::MemoryOp`][acir::circuit::Opcode::MemoryOp], routing it to the
+    /// [`MemoryOpSolver`] for `block_id`. `acir_index` is this opcode's position within the
+    /// circuit, the opcode-solving loop's position counter; it is wrapped into the
+    /// [`ErrorLocation`] so that any error, and any trace entry recorded while tracing is
+    /// enabled, can be tied back to the opcode that produced it.
+    pub(crate) fn solve_memory_op(
+        &mut self,
+        block_id: BlockId,
+        op: &MemOp<F>,
+        initial_witness: &mut WitnessMap<F>,
+        predicate: &Option<Expression<F>>,
+        pedantic_solving: bool,
+        acir_index: usize,
+    ) -> Result<(), OpcodeResolutionError<F>> {
+        let opcode_location =
+            ErrorLocation::Resolved(acir::circuit::OpcodeLocation::Acir(acir_index));
+        self.block_solvers.entry(block_id).or_default().solve_memory_op(
+            op,
+            initial_witness,
+            predicate,
+            pedantic_solving,
+            opcode_location,
+        )
+    }
+}